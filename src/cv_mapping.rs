@@ -0,0 +1,188 @@
+//! Data-driven controlled-vocabulary mapping for SDRF columns.
+//!
+//! [`SDRFField::as_param`](crate::SDRFField::as_param) used to hardcode every column -> CURIE
+//! mapping (and every TMT label) in a `match` statement. That knowledge now lives in a
+//! [`CvMappingTable`], which can be deserialized from an external TOML file so that new
+//! ontology terms or label schemes (iTRAQ, SILAC, ...) can be added without recompiling.
+//! [`CvMappingTable::built_in`] reproduces the historical hardcoded table exactly, so a run
+//! without a mapping file (the optional second command-line argument) behaves as it always has.
+
+use std::{collections::HashMap, fs, io, path::Path, str::FromStr};
+
+use mzdata::params::{ControlledVocabulary, CURIE};
+use serde::Deserialize;
+
+/// The CV term an SDRF column should be mapped to.
+///
+/// A plain column (e.g. `organism`) sets `cv`/`accession`/`name` and leaves `labels` empty.
+/// A label-like column (e.g. `label`, for TMT/iTRAQ channels) instead leaves `cv` unset and
+/// provides a value-keyed `labels` sub-table, since the CV term depends on the cell's value
+/// rather than the column itself.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CvMappingEntry {
+    /// Short prefix of the target controlled vocabulary, e.g. `"MS"` or `"EFO"`.
+    #[serde(default)]
+    pub cv: Option<String>,
+    #[serde(default)]
+    pub accession: Option<u32>,
+    /// Display name to carry on the emitted `cvParam`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Value-keyed sub-table for label-like columns, e.g. `"TMT126" -> MS:1002616`.
+    #[serde(default)]
+    pub labels: HashMap<String, CvLabelEntry>,
+}
+
+/// The CV term a single label value (one row of `labels`) should be mapped to.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CvLabelEntry {
+    pub cv: String,
+    pub accession: u32,
+    pub name: String,
+}
+
+impl CvMappingEntry {
+    /// The CURIE for this column's own term, if it has one (label-dispatch entries don't).
+    pub fn curie(&self) -> Option<CURIE> {
+        let cv = ControlledVocabulary::from_str(self.cv.as_deref()?).ok()?;
+        Some(CURIE::new(cv, self.accession?))
+    }
+}
+
+impl CvLabelEntry {
+    pub fn curie(&self) -> Option<CURIE> {
+        ControlledVocabulary::from_str(&self.cv)
+            .ok()
+            .map(|cv| CURIE::new(cv, self.accession))
+    }
+}
+
+/// A table of normalized SDRF column name -> [`CvMappingEntry`].
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CvMappingTable {
+    #[serde(flatten)]
+    entries: HashMap<String, CvMappingEntry>,
+}
+
+impl CvMappingTable {
+    /// Load a mapping table from a TOML file on disk, e.g. one shipped alongside a project's
+    /// own SDRF files to add terms this tool doesn't know about, and merge it on top of
+    /// [`built_in`](Self::built_in): a column the file doesn't mention keeps its built-in
+    /// mapping, a column it does mention has its `cv`/`accession`/`name` overridden, and its
+    /// `labels` are added to (not replacing) any built-in labels for that column.
+    pub fn load_from_path(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let overrides: Self =
+            toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut table = Self::built_in();
+        table.merge(overrides);
+        Ok(table)
+    }
+
+    /// Layer `other`'s entries on top of `self`, overriding/extending rather than replacing.
+    fn merge(&mut self, other: Self) {
+        for (column, entry) in other.entries {
+            match self.entries.get_mut(&column) {
+                Some(existing) => {
+                    if entry.cv.is_some() {
+                        existing.cv = entry.cv;
+                    }
+                    if entry.accession.is_some() {
+                        existing.accession = entry.accession;
+                    }
+                    if entry.name.is_some() {
+                        existing.name = entry.name;
+                    }
+                    existing.labels.extend(entry.labels);
+                }
+                None => {
+                    self.entries.insert(column, entry);
+                }
+            }
+        }
+    }
+
+    /// Look up the mapping for an already-normalized (lowercase, unbracketed) column name.
+    pub fn get(&self, name: &str) -> Option<&CvMappingEntry> {
+        self.entries.get(name)
+    }
+
+    /// The mapping table baked into the binary, preserving the mappings `as_param` used to
+    /// hardcode before this module existed.
+    pub fn built_in() -> Self {
+        let mut entries = HashMap::new();
+
+        let simple = [
+            ("organism part", "EFO", 635, "organism part"),
+            ("organism", "OBI", 100026, "organism"),
+            ("developmental stage", "EFO", 399, "developmental stage"),
+            ("ancestry category", "HANCESTRO", 4, "ancestry category"),
+            ("cell type", "EFO", 324, "cell type"),
+            ("material type", "BFO", 40, "material type"),
+            ("age", "EFO", 246, "age"),
+            ("disease", "EFO", 408, "disease"),
+            ("time", "EFO", 721, "time"),
+            ("technology type", "EFO", 5521, "technology type"),
+            ("biological replicate", "EFO", 2091, "biological replicate"),
+            ("technical replicate", "MS", 1001808, "technical replicate"),
+            ("fraction identifier", "MS", 1000858, "fraction identifier"),
+            ("file uri", "PRIDE", 577, "file uri"),
+        ];
+        for (column, cv, accession, name) in simple {
+            entries.insert(
+                column.to_string(),
+                CvMappingEntry {
+                    cv: Some(cv.to_string()),
+                    accession: Some(accession),
+                    name: Some(name.to_string()),
+                    labels: HashMap::new(),
+                },
+            );
+        }
+
+        // TMT labels (and probably other isobaric labels)
+        // TODO: The MS controlled vocabulary has specific terms for these labels, but the PRIDE
+        // CV seems to have its own terms for them, sometimes in multiples? Which CV would it
+        // make sense to use here?
+        let tmt_labels = [
+            ("TMT126", 1002616, "TMT reagent 126"),
+            ("TMT127", 1002617, "TMT reagent 127"),
+            ("TMT128", 1002618, "TMT reagent 128"),
+            ("TMT129", 1002619, "TMT reagent 129"),
+            ("TMT130", 1002620, "TMT reagent 130"),
+            ("TMT131", 1002621, "TMT reagent 131"),
+            ("TMT127N", 1002763, "TMT reagent 127N"),
+            ("TMT127C", 1002764, "TMT reagent 127C"),
+            ("TMT128N", 1002765, "TMT reagent 128N"),
+            ("TMT128C", 1002766, "TMT reagent 128C"),
+            ("TMT129N", 1002767, "TMT reagent 129N"),
+            ("TMT129C", 1002768, "TMT reagent 129C"),
+            ("TMT130N", 1002769, "TMT reagent 130N"),
+            ("TMT130C", 1002770, "TMT reagent 130C"),
+        ];
+        let labels = tmt_labels
+            .into_iter()
+            .map(|(value, accession, name)| {
+                (
+                    value.to_string(),
+                    CvLabelEntry {
+                        cv: "MS".to_string(),
+                        accession,
+                        name: name.to_string(),
+                    },
+                )
+            })
+            .collect();
+        entries.insert(
+            "label".to_string(),
+            CvMappingEntry {
+                cv: None,
+                accession: None,
+                name: None,
+                labels,
+            },
+        );
+
+        Self { entries }
+    }
+}
@@ -0,0 +1,40 @@
+//! A small string interner, mirroring the interned-identifier design used elsewhere: a
+//! [`Symbol`] is a small `Copy` id whose equality/hashing only ever compares the id, never
+//! any positional provenance, so distinct occurrences of the same string always intern to
+//! the same `Symbol`.
+
+use std::{collections::HashMap, sync::Arc};
+
+/// An interned string id. Cheap to copy, compare, and hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Symbol(u32);
+
+/// Interns strings into [`Symbol`]s, storing each distinct string exactly once.
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    ids: HashMap<Box<str>, u32>,
+    strings: Vec<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Intern `s`, returning its existing [`Symbol`] or allocating a new one.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&id) = self.ids.get(s) {
+            return Symbol(id);
+        }
+        let id = self.strings.len() as u32;
+        let arc: Arc<str> = Arc::from(s);
+        self.strings.push(arc);
+        self.ids.insert(Box::from(s), id);
+        Symbol(id)
+    }
+
+    /// Resolve a [`Symbol`] back to the string it was interned from.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+}
@@ -2,18 +2,70 @@
 //! modifying it en route to include a sample list with metadata
 //! drawn from the SDRF file
 
-use std::{borrow::Cow, collections::HashMap, env, io, path, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    env, fmt, io, path,
+};
 
 use csv;
 use log::info;
 use mzdata::{
-    curie,
     io::{MzMLReader, MzMLWriter, StreamingSpectrumIterator},
     meta::Sample,
     params::{Param, ParamValue, Value},
     prelude::*,
 };
 
+mod cv_mapping;
+mod intern;
+mod sdrf_annotation;
+use cv_mapping::CvMappingTable;
+use intern::{Interner, Symbol};
+use sdrf_annotation::SDRFAnnotation;
+
+/// Diagnostic raised when a single SDRF column can't be parsed, so one malformed header
+/// produces a targeted error rather than aborting with a panic.
+#[derive(Debug)]
+enum SdrfError {
+    /// A `characteristics[...]`/`comment[...]`/`factor value[...]` header is missing its
+    /// closing bracket, or otherwise isn't balanced.
+    MalformedColumn(String),
+    /// No `comment[data file]` column could be found for a row.
+    MissingDataFileColumn,
+}
+
+impl fmt::Display for SdrfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdrfError::MalformedColumn(column) => {
+                write!(f, "could not parse SDRF column header {column:?}: unbalanced brackets")
+            }
+            SdrfError::MissingDataFileColumn => {
+                write!(f, "SDRF row is missing a \"comment[data file]\" column")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SdrfError {}
+
+impl From<SdrfError> for io::Error {
+    fn from(e: SdrfError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+    }
+}
+
+/// Lowercase and trim a raw SDRF header so that e.g. `" Characteristics[ Organism ]"` and
+/// `"characteristics[organism]"` classify and name identically.
+fn normalize_header(raw: &str) -> String {
+    raw.trim()
+        .to_ascii_lowercase()
+        .replace(" [", "[")
+        .replace("[ ", "[")
+        .replace(" ]", "]")
+        .replace("] ", "]")
+}
+
 /// Describe a column class tag
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 enum SDRFClass {
@@ -28,81 +80,108 @@ enum SDRFClass {
 /// Represent a row value for a single column in an SDRF table
 #[derive(Default, Clone)]
 struct SDRFField {
-    name: Arc<String>,
+    name: Symbol,
     field_class: SDRFClass,
     value: Value,
+    /// Structured `NT=`/`AC=`/`TA=`/`CS=` annotation, if the cell used that microsyntax.
+    annotation: Option<SDRFAnnotation>,
 }
 
 impl SDRFField {
 
-    /// Convert this column into an mzML-compatible parameter, either as a cvParam or userParam
-    fn as_param(&self) -> Param {
-        let name = self.name();
-
-        // Check to see if the column is one we have a clear controlled vocabulary mapping for
-        let curie_of = match name {
-            "organism part" => Some((curie!(EFO:0000635), name, self.value.clone())),
-            "organism" => Some((curie!(OBI:0100026), name, self.value.clone())),
-            "developmental stage" => Some((curie!(EFO:0000399), name, self.value.clone())),
-            "ancestry category" => Some((curie!(HANCESTRO:0004), name, self.value.clone())),
-            "cell type" => Some((curie!(EFO:0000324), name, self.value.clone())),
-            "material type" => Some((curie!(BFO:0000040), name, self.value.clone())),
-            "age" => Some((curie!(EFO:0000246), name, self.value.clone())),
-            "disease" => Some((curie!(EFO:0000408), name, self.value.clone())),
-
-            "time" => Some((curie!(EFO:0000721), name, self.value.clone())),
-            "technology type" => Some((curie!(EFO:0005521), name, self.value.clone())),
-
-            "biological replicate" => Some((curie!(EFO:0002091), name, self.value.clone())),
-            "technical replicate" => Some((curie!(MS:1001808), name, self.value.clone())),
-            "fraction identifier" => Some((curie!(MS:1000858), name, self.value.clone())),
-
-            "file uri" => Some((curie!(PRIDE:0000577), name, self.value.clone())),
-
-            // TMT labels (and probably other isobaric labels)
-            // TODO: The MS controlled vocabulary has specific terms for these labels, but the PRIDE CV seems to
-            // have its own terms for them, sometimes in multiples? Which CV would it make sense to use here?
-            "label" => match self.value.as_str().as_ref() {
-                "TMT126" => Some((curie!(MS:1002616), "TMT reagent 126", Value::Empty)),
-                "TMT127" => Some((curie!(MS:1002617), "TMT reagent 127", Value::Empty)),
-                "TMT128" => Some((curie!(MS:1002618), "TMT reagent 128", Value::Empty)),
-                "TMT129" => Some((curie!(MS:1002619), "TMT reagent 129", Value::Empty)),
-                "TMT130" => Some((curie!(MS:1002620), "TMT reagent 130", Value::Empty)),
-                "TMT131" => Some((curie!(MS:1002621), "TMT reagent 131", Value::Empty)),
-                "TMT127N" => Some((curie!(MS:1002763), "TMT reagent 127N", Value::Empty)),
-                "TMT127C" => Some((curie!(MS:1002764), "TMT reagent 127C", Value::Empty)),
-                "TMT128N" => Some((curie!(MS:1002765), "TMT reagent 128N", Value::Empty)),
-                "TMT128C" => Some((curie!(MS:1002766), "TMT reagent 128C", Value::Empty)),
-                "TMT129N" => Some((curie!(MS:1002767), "TMT reagent 129N", Value::Empty)),
-                "TMT129C" => Some((curie!(MS:1002768), "TMT reagent 129C", Value::Empty)),
-                "TMT130N" => Some((curie!(MS:1002769), "TMT reagent 130N", Value::Empty)),
-                "TMT130C" => Some((curie!(MS:1002770), "TMT reagent 130C", Value::Empty)),
-                _ => None,
-            },
-            _ => None,
+    /// Convert this column into an mzML-compatible parameter, either as a cvParam or userParam.
+    ///
+    /// The controlled vocabulary mapping comes from `cv_map`, which is either the built-in
+    /// table or one loaded from a user-supplied config file; columns it doesn't recognize
+    /// fall back to a userParam.
+    fn as_param(&self, cv_map: &CvMappingTable, interner: &Interner) -> Result<Param, SdrfError> {
+        let name = self.name(interner)?;
+
+        if let Some(entry) = cv_map.get(name) {
+            if !entry.labels.is_empty() {
+                // Label-like column: the CV term depends on the cell's value, not the column.
+                if let Some(label) = entry.labels.get(self.value.as_str().as_ref()) {
+                    if let Some(curie) = label.curie() {
+                        return Ok(curie.controlled_vocabulary.param_val(
+                            curie.accession,
+                            label.name.as_str(),
+                            Value::Empty,
+                        ));
+                    }
+                }
+            } else if let Some(curie) = entry.curie() {
+                return Ok(curie.controlled_vocabulary.param_val(
+                    curie.accession,
+                    entry.name.as_deref().unwrap_or(name),
+                    self.value.clone(),
+                ));
+            }
+        }
+        Ok(Param::new_key_value(
+            interner.resolve(self.name).to_string(),
+            self.value.clone(),
+        ))
+    }
+
+    /// Convert this column into one or more mzML-compatible parameters.
+    ///
+    /// When the cell carries its own `AC=` accession (see [`SDRFAnnotation`]) that resolves to
+    /// a CURIE mzdata recognizes, that explicit CURIE and `NT=` term take priority over the
+    /// column-based mapping. If the `AC=` prefix isn't one mzdata knows (so no CURIE can be
+    /// built), this falls back to [`SDRFField::as_param`] for the column-mapped param, but
+    /// still emits the raw accession as a userParam rather than silently dropping it. Either
+    /// way, any `TA=` source ontology, `CS=` comparison string, or other annotation keys are
+    /// emitted as additional userParams.
+    fn as_params(&self, cv_map: &CvMappingTable, interner: &Interner) -> Result<Vec<Param>, SdrfError> {
+        let Some(annotation) = self.annotation.as_ref() else {
+            return Ok(vec![self.as_param(cv_map, interner)?]);
+        };
+
+        let mut params = match annotation.curie(interner) {
+            Some(curie) => {
+                let name = match annotation.term_str(interner) {
+                    Some(name) => name.to_string(),
+                    None => self.name(interner)?.to_string(),
+                };
+                vec![curie
+                    .controlled_vocabulary
+                    .param_val(curie.accession, name, Value::Empty)]
+            }
+            None => {
+                let mut params = vec![self.as_param(cv_map, interner)?];
+                if let Some(ac) = annotation.accession_str(interner) {
+                    params.push(Param::new_key_value("AC".to_string(), ac.parse().unwrap()));
+                }
+                params
+            }
         };
-        if let Some((curie_of, name, value)) = curie_of {
-            curie_of
-                .controlled_vocabulary
-                .param_val(curie_of.accession, name, value)
-        } else {
-            Param::new_key_value(self.name.to_string(), self.value.clone())
+        if let Some(ta) = annotation.source_ontology_str(interner) {
+            params.push(Param::new_key_value("TA".to_string(), ta.parse().unwrap()));
+        }
+        if let Some(cs) = annotation.comparison_str(interner) {
+            params.push(Param::new_key_value("CS".to_string(), cs.parse().unwrap()));
         }
+        for (key, value) in &annotation.extra {
+            params.push(Param::new_key_value(
+                interner.resolve(*key).to_string(),
+                interner.resolve(*value).parse().unwrap(),
+            ));
+        }
+        Ok(params)
     }
 
-    /// Extract the name of the column, independent of its column class
-    fn name(&self) -> &str {
+    /// Extract the name of the column, independent of its column class. Returns an error,
+    /// rather than panicking, when a `characteristics[...]`/`comment[...]`/`factor
+    /// value[...]` header has unbalanced brackets.
+    fn name<'a>(&self, interner: &'a Interner) -> Result<&'a str, SdrfError> {
+        let raw = interner.resolve(self.name);
         match self.field_class {
-            SDRFClass::Innate | SDRFClass::Factor => self.name.as_str(),
-            _ => self
-                .name
+            SDRFClass::Innate | SDRFClass::Factor => Ok(raw),
+            _ => raw
                 .split_once('[')
-                .unwrap()
-                .1
-                .rsplit_once(']')
-                .unwrap()
-                .0
-                .trim(),
+                .and_then(|(_, rest)| rest.rsplit_once(']'))
+                .map(|(inner, _)| inner.trim())
+                .ok_or_else(|| SdrfError::MalformedColumn(raw.to_string())),
         }
     }
 }
@@ -117,14 +196,19 @@ struct SDRFSample {
 }
 
 impl SDRFSample {
-    fn data_file(&self) -> Option<Cow<'_, str>> {
-        self.comments
-            .iter()
-            .find(|f| f.name() == "data file")
-            .map(|f| f.value.as_str())
+    /// The `comment[data file]` value for this sample, interned so it can be compared
+    /// cheaply against the mzML source file name.
+    fn data_file(&self, interner: &mut Interner) -> Result<Symbol, SdrfError> {
+        for f in &self.comments {
+            if f.name(interner)? == "data file" {
+                let value = f.value.as_str().to_string();
+                return Ok(interner.intern(&value));
+            }
+        }
+        Err(SdrfError::MissingDataFileColumn)
     }
 
-    fn as_sample(&self) -> Sample {
+    fn as_sample(&self, cv_map: &CvMappingTable, interner: &Interner) -> Result<Sample, SdrfError> {
         let mut params = Vec::new();
         for field in self
             .fields
@@ -133,33 +217,60 @@ impl SDRFSample {
             .chain(self.comments.iter())
             .chain(self.factors.iter())
         {
-            match field.name() {
+            match field.name(interner)? {
                 "data file" | "instrument" => {}
-                _ => params.push(field.as_param()),
+                _ => params.extend(field.as_params(cv_map, interner)?),
             }
         }
-        Sample::new(
+        // NOTE: replicate/fraction/label distinctions are not nested as sub-parameters of a
+        // parent characteristic here; they're chained into the same flat `params` list as
+        // everything else. mzdata's `Param` doesn't carry child params, so there's nowhere to
+        // hang a real hierarchy without inventing one — [`group_replicates`] dedup is what
+        // keeps this list compact, not structure.
+        Ok(Sample::new(
             self.name.replace(" ", "_").to_lowercase(),
             Some(self.name.to_string()),
             params,
-        )
+        ))
+    }
+}
+
+/// Build a single [`SDRFField`] from a raw cell, parsing out an [`SDRFAnnotation`] when the
+/// cell uses the `key=value;` microsyntax; plain strings (including `not applicable`) parse
+/// into `value` exactly as before.
+fn build_field(name: Symbol, field_class: SDRFClass, val: &str, interner: &mut Interner) -> SDRFField {
+    let annotation = SDRFAnnotation::parse(val, interner);
+    let value = annotation
+        .as_ref()
+        .and_then(|a| a.term_str(interner))
+        .unwrap_or(val)
+        .parse()
+        .unwrap();
+    SDRFField {
+        name,
+        field_class,
+        value,
+        annotation,
     }
 }
 
 /// Actually read the SDRF file into row-level [`SDRFSample`].
 ///
-/// Makes no effort to aggregate replicates
-fn read_sdrf(sdrf_path: &path::Path) -> io::Result<Vec<SDRFSample>> {
+/// Each row becomes its own `SDRFSample`; [`group_replicates`] is responsible for
+/// collapsing rows that describe the same biological source.
+fn read_sdrf(sdrf_path: &path::Path, interner: &mut Interner) -> io::Result<Vec<SDRFSample>> {
     let mut reader = csv::ReaderBuilder::new()
         .delimiter(b'\t')
         .has_headers(true)
         .from_path(sdrf_path)?;
 
-    // Read and normalize the column names. These will be re-used over rows of [`SDRFField`].
-    let headers: Vec<_> = reader
+    // Read and normalize the column names, interning each one. These will be re-used over
+    // rows of [`SDRFField`], so interning lets every row refer to them as a `Copy` id
+    // instead of cloning an `Arc<String>`.
+    let headers: Vec<Symbol> = reader
         .headers()?
         .iter()
-        .map(|s| Arc::new(s.to_string().replace(" ]", "]")))
+        .map(|s| interner.intern(&normalize_header(s)))
         .collect();
 
     // Parse the rows into [`SDRFSample`] instances
@@ -168,43 +279,29 @@ fn read_sdrf(sdrf_path: &path::Path) -> io::Result<Vec<SDRFSample>> {
         match row {
             Ok(row) => {
                 let mut sample = SDRFSample::default();
-                for (name, val) in headers.iter().zip(row.iter()) {
-                    match name.as_str() {
-                        "source name" => sample.name = val.into(),
-                        x if x.starts_with("characteristics[")
-                            || x.starts_with("characteristic[") =>
-                        {
-                            let f = SDRFField {
-                                name: Arc::clone(name),
-                                field_class: SDRFClass::Characteristic,
-                                value: val.parse().unwrap(),
-                            };
-                            sample.characteristics.push(f);
-                        }
-                        x if x.starts_with("comment[") => {
-                            let f = SDRFField {
-                                name: name.clone(),
-                                field_class: SDRFClass::Comment,
-                                value: val.parse().unwrap(),
-                            };
-                            sample.comments.push(f);
-                        }
-                        x if x.starts_with("factor value[") => {
-                            let f = SDRFField {
-                                name: Arc::clone(name),
-                                field_class: SDRFClass::Factor,
-                                value: val.parse().unwrap(),
-                            };
-                            sample.factors.push(f);
-                        }
-                        _ => {
-                            let f = SDRFField {
-                                name: Arc::clone(name),
-                                field_class: SDRFClass::Innate,
-                                value: val.parse().unwrap(),
-                            };
-                            sample.fields.push(f);
-                        }
+                for (&name, val) in headers.iter().zip(row.iter()) {
+                    let header = interner.resolve(name);
+                    if header == "source name" {
+                        sample.name = val.into();
+                        continue;
+                    }
+                    let field_class = if header.starts_with("characteristics[")
+                        || header.starts_with("characteristic[")
+                    {
+                        SDRFClass::Characteristic
+                    } else if header.starts_with("comment[") {
+                        SDRFClass::Comment
+                    } else if header.starts_with("factor value[") {
+                        SDRFClass::Factor
+                    } else {
+                        SDRFClass::Innate
+                    };
+                    let f = build_field(name, field_class, val, interner);
+                    match field_class {
+                        SDRFClass::Characteristic => sample.characteristics.push(f),
+                        SDRFClass::Comment => sample.comments.push(f),
+                        SDRFClass::Factor => sample.factors.push(f),
+                        SDRFClass::Innate => sample.fields.push(f),
                     }
                 }
                 samples.push(sample);
@@ -220,15 +317,74 @@ fn read_sdrf(sdrf_path: &path::Path) -> io::Result<Vec<SDRFSample>> {
 }
 
 /// Re-arrange the samples into groups organized by the "comment[data file]" field
-fn organize_by_data_file(sdrf_samples: Vec<SDRFSample>) -> HashMap<String, Vec<SDRFSample>> {
-    let mut index: HashMap<String, Vec<SDRFSample>> = HashMap::new();
+fn organize_by_data_file(
+    sdrf_samples: Vec<SDRFSample>,
+    interner: &mut Interner,
+) -> Result<HashMap<Symbol, Vec<SDRFSample>>, SdrfError> {
+    let mut index: HashMap<Symbol, Vec<SDRFSample>> = HashMap::new();
     for s in sdrf_samples {
-        index
-            .entry(s.data_file().unwrap().to_string())
-            .or_default()
-            .push(s);
+        let key = s.data_file(interner)?;
+        index.entry(key).or_default().push(s);
+    }
+    Ok(index)
+}
+
+/// Collapse SDRF rows that share both a `source name` and an identical set of
+/// `characteristics[...]` values into a single [`SDRFSample`] per source, deduplicating fields
+/// that are identical across rows while keeping every distinct value for fields that differ,
+/// such as `technical replicate`, `fraction identifier`, or `label`. Rows that share a source
+/// name but disagree on a characteristic (e.g. a time course or disease-state series that
+/// reuses one source name across timepoints/states) are kept as separate samples rather than
+/// merged into one with conflicting `disease`/`organism part`/etc. params.
+///
+/// Note this only collapses label-multiplexed (TMT/iTRAQ) channel rows into one sample when
+/// those rows actually share a `source name` and characteristics, differing only in `label`.
+/// Real-world TMT SDRFs commonly give each channel its own distinct `source name` instead (one
+/// row per channel, same `comment[data file]`), in which case this pass leaves them as separate
+/// samples — grouping those would require keying on `comment[data file]` instead of `source
+/// name`, which isn't what this function does.
+fn group_replicates(samples: &[SDRFSample]) -> Vec<SDRFSample> {
+    let mut groups: Vec<(SDRFSample, BTreeSet<(Symbol, String)>)> = Vec::new();
+    for sample in samples {
+        let key = characteristics_key(sample);
+        match groups
+            .iter_mut()
+            .find(|(g, k)| g.name == sample.name && *k == key)
+        {
+            Some((existing, _)) => {
+                merge_fields(&mut existing.fields, &sample.fields);
+                merge_fields(&mut existing.characteristics, &sample.characteristics);
+                merge_fields(&mut existing.comments, &sample.comments);
+                merge_fields(&mut existing.factors, &sample.factors);
+            }
+            None => groups.push((sample.clone(), key)),
+        }
+    }
+    groups.into_iter().map(|(g, _)| g).collect()
+}
+
+/// The deduplicated `(column, value)` pairs from a sample's characteristics, used as the
+/// other half of [`group_replicates`]'s grouping identity alongside `source name`.
+fn characteristics_key(sample: &SDRFSample) -> BTreeSet<(Symbol, String)> {
+    sample
+        .characteristics
+        .iter()
+        .map(|f| (f.name, f.value.as_str().to_string()))
+        .collect()
+}
+
+/// Append fields from `incoming` onto `existing` that aren't already present (same column
+/// and value), so repeated characteristics collapse to one copy while distinct replicate/
+/// fraction/label values all survive.
+fn merge_fields(existing: &mut Vec<SDRFField>, incoming: &[SDRFField]) {
+    for field in incoming {
+        let duplicate = existing
+            .iter()
+            .any(|f| f.name == field.name && f.value.as_str() == field.value.as_str());
+        if !duplicate {
+            existing.push(field.clone());
+        }
     }
-    index
 }
 
 /// Consume the mzML stream from STDIN and write it through to STDOUT
@@ -251,28 +407,154 @@ fn write_passthrough<R: io::Read>(reader: MzMLReader<R>) -> io::Result<()> {
 
 /// Patch the metadata with the samples that correspond to this data file, as given by the "first"
 /// source file in this mzML file
-fn update_sample_list<R: io::Read>(reader: &mut MzMLReader<R>, samples: &[SDRFSample]) {
+fn update_sample_list<R: io::Read>(
+    reader: &mut MzMLReader<R>,
+    samples: &[SDRFSample],
+    cv_map: &CvMappingTable,
+    interner: &Interner,
+) -> Result<(), SdrfError> {
     let samples_of = reader.samples_mut();
     samples_of.clear();
-    samples_of.extend(samples.iter().map(|s| s.as_sample()));
+    for sample in samples {
+        samples_of.push(sample.as_sample(cv_map, interner)?);
+    }
     info!("Updated sample list metadata");
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
     pretty_env_logger::init_timed();
-    let sdrf_path = path::PathBuf::from(env::args().skip(1).next().unwrap());
-    let samples = read_sdrf(&sdrf_path)?;
-    let samples_by_data_file = organize_by_data_file(samples);
+    let mut args = env::args().skip(1);
+    let sdrf_path = path::PathBuf::from(args.next().unwrap());
+    // An optional second argument points to a TOML file of additional/overriding CV mappings;
+    // without one, the built-in table reproduces today's hardcoded behavior.
+    let cv_map = match args.next() {
+        Some(path) => CvMappingTable::load_from_path(path::Path::new(&path))?,
+        None => CvMappingTable::built_in(),
+    };
+
+    let mut interner = Interner::new();
+    let samples = read_sdrf(&sdrf_path, &mut interner)?;
+    let samples_by_data_file = organize_by_data_file(samples, &mut interner)?;
 
     let mut reader = MzMLReader::new(io::stdin());
 
     let source_file = reader.file_description().source_files.first().unwrap();
     log::info!("Extracting samples associated with {}", source_file.name);
-    let samples = samples_by_data_file.get(&source_file.name).unwrap();
-    log::info!("Found {} samples", samples.len());
+    let source_file_name = interner.intern(&source_file.name);
+    let samples = samples_by_data_file.get(&source_file_name).unwrap();
+    log::info!("Found {} rows", samples.len());
+    let samples = group_replicates(samples);
+    log::info!("Aggregated into {} samples", samples.len());
 
-    update_sample_list(&mut reader, &samples);
+    update_sample_list(&mut reader, &samples, &cv_map, &interner)?;
 
     write_passthrough(reader)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_header_trims_case_and_bracket_spacing() {
+        assert_eq!(
+            normalize_header(" Characteristics[ Organism ]"),
+            "characteristics[organism]"
+        );
+        assert_eq!(normalize_header("Comment[data file]"), "comment[data file]");
+    }
+
+    #[test]
+    fn malformed_column_header_is_an_error_not_a_panic() {
+        let mut interner = Interner::new();
+        let name = interner.intern("characteristics[organism");
+        let field = SDRFField {
+            name,
+            field_class: SDRFClass::Characteristic,
+            value: Value::Empty,
+            annotation: None,
+        };
+        let err = field.name(&interner).unwrap_err();
+        assert!(matches!(err, SdrfError::MalformedColumn(col) if col == "characteristics[organism"));
+    }
+
+    #[test]
+    fn group_replicates_keeps_differing_characteristics_separate() {
+        let mut interner = Interner::new();
+        let disease_col = interner.intern("characteristics[disease]");
+
+        let mut healthy = SDRFSample {
+            name: "source-1".to_string(),
+            ..Default::default()
+        };
+        healthy.characteristics.push(build_field(
+            disease_col,
+            SDRFClass::Characteristic,
+            "normal",
+            &mut interner,
+        ));
+
+        let mut diseased = SDRFSample {
+            name: "source-1".to_string(),
+            ..Default::default()
+        };
+        diseased.characteristics.push(build_field(
+            disease_col,
+            SDRFClass::Characteristic,
+            "cancer",
+            &mut interner,
+        ));
+
+        let grouped = group_replicates(&[healthy, diseased]);
+        assert_eq!(
+            grouped.len(),
+            2,
+            "rows sharing a source name but disagreeing on a characteristic must not merge"
+        );
+    }
+
+    #[test]
+    fn group_replicates_collapses_identical_sources_differing_only_in_label() {
+        let mut interner = Interner::new();
+        let disease_col = interner.intern("characteristics[disease]");
+        let label_col = interner.intern("comment[label]");
+
+        let mut row_a = SDRFSample {
+            name: "source-1".to_string(),
+            ..Default::default()
+        };
+        row_a.characteristics.push(build_field(
+            disease_col,
+            SDRFClass::Characteristic,
+            "normal",
+            &mut interner,
+        ));
+        row_a
+            .comments
+            .push(build_field(label_col, SDRFClass::Comment, "TMT126", &mut interner));
+
+        let mut row_b = SDRFSample {
+            name: "source-1".to_string(),
+            ..Default::default()
+        };
+        row_b.characteristics.push(build_field(
+            disease_col,
+            SDRFClass::Characteristic,
+            "normal",
+            &mut interner,
+        ));
+        row_b
+            .comments
+            .push(build_field(label_col, SDRFClass::Comment, "TMT127", &mut interner));
+
+        let grouped = group_replicates(&[row_a, row_b]);
+        assert_eq!(grouped.len(), 1, "identical source name and characteristics should collapse");
+        assert_eq!(
+            grouped[0].comments.len(),
+            2,
+            "distinct label values must both survive the merge"
+        );
+    }
+}
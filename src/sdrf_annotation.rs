@@ -0,0 +1,132 @@
+//! Parser for the `key=value;` microsyntax SDRF uses to embed ontology annotations directly
+//! in a cell, e.g. `NT=cerebellum;AC=UBERON:0002037;TA=UBERON`.
+
+use std::str::FromStr;
+
+use mzdata::params::{ControlledVocabulary, CURIE};
+
+use crate::intern::{Interner, Symbol};
+
+/// A parsed `NT=`/`AC=`/`TA=`/`CS=` (and friends) ontology annotation cell.
+///
+/// Fields are [`Symbol`]s rather than owned `String`s: the same term/accession recurs across
+/// many rows of a large SDRF, so interning avoids re-allocating it every time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SDRFAnnotation {
+    /// `NT=`, the human-readable term name.
+    pub term: Option<Symbol>,
+    /// `AC=`, the accession, e.g. `"UBERON:0002037"`.
+    pub accession: Option<Symbol>,
+    /// `TA=`, the source ontology the accession belongs to.
+    pub source_ontology: Option<Symbol>,
+    /// `CS=`, a comparison string (e.g. `">10"`), carried through as-is.
+    pub comparison: Option<Symbol>,
+    /// Any other `key=value` pairs found in the cell (e.g. unit annotations), in order.
+    pub extra: Vec<(Symbol, Symbol)>,
+}
+
+impl SDRFAnnotation {
+    /// Parse a `key=value;key=value;...` cell. Returns `None` if the cell contains no
+    /// `key=value` pairs, or if any non-empty `;`-separated part lacks an `=`, so plain
+    /// unannotated strings (including `not applicable`) are left for the caller to handle as
+    /// before.
+    pub fn parse(raw: &str, interner: &mut Interner) -> Option<Self> {
+        let mut annotation = SDRFAnnotation::default();
+        let mut found_pair = false;
+        for part in raw.split(';') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = part.split_once('=') else {
+                return None;
+            };
+            found_pair = true;
+            let value = interner.intern(value.trim());
+            match key.trim().to_ascii_uppercase().as_str() {
+                "NT" => annotation.term = Some(value),
+                "AC" => annotation.accession = Some(value),
+                "TA" => annotation.source_ontology = Some(value),
+                "CS" => annotation.comparison = Some(value),
+                other => annotation.extra.push((interner.intern(other), value)),
+            }
+        }
+        found_pair.then_some(annotation)
+    }
+
+    /// Resolve the `NT=` term name, if present.
+    pub fn term_str<'a>(&self, interner: &'a Interner) -> Option<&'a str> {
+        self.term.map(|s| interner.resolve(s))
+    }
+
+    /// Resolve the `CS=` comparison string, if present.
+    pub fn comparison_str<'a>(&self, interner: &'a Interner) -> Option<&'a str> {
+        self.comparison.map(|s| interner.resolve(s))
+    }
+
+    /// Resolve the raw `AC=` accession string, if present.
+    pub fn accession_str<'a>(&self, interner: &'a Interner) -> Option<&'a str> {
+        self.accession.map(|s| interner.resolve(s))
+    }
+
+    /// Resolve the `TA=` source ontology name, if present.
+    pub fn source_ontology_str<'a>(&self, interner: &'a Interner) -> Option<&'a str> {
+        self.source_ontology.map(|s| interner.resolve(s))
+    }
+
+    /// Build the explicit CURIE this annotation specifies via `AC=`, if any, splitting the
+    /// CV prefix from the accession number.
+    pub fn curie(&self, interner: &Interner) -> Option<CURIE> {
+        let ac = interner.resolve(self.accession?);
+        let (prefix, accession) = ac.split_once(':')?;
+        let cv = ControlledVocabulary::from_str(prefix).ok()?;
+        let accession: u32 = accession.parse().ok()?;
+        Some(CURIE::new(cv, accession))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nt_ac_into_curie() {
+        let mut interner = Interner::new();
+        let annotation = SDRFAnnotation::parse("NT=cerebellum;AC=UBERON:0002037", &mut interner)
+            .expect("a NT=/AC= cell should parse");
+        assert_eq!(annotation.term_str(&interner), Some("cerebellum"));
+        let curie = annotation
+            .curie(&interner)
+            .expect("a known CV prefix should resolve to a CURIE");
+        assert_eq!(curie.accession, 2037);
+    }
+
+    #[test]
+    fn ta_and_cs_are_resolvable() {
+        let mut interner = Interner::new();
+        let annotation =
+            SDRFAnnotation::parse("NT=cerebellum;AC=UBERON:0002037;TA=UBERON;CS=>10", &mut interner)
+                .unwrap();
+        assert_eq!(annotation.source_ontology_str(&interner), Some("UBERON"));
+        assert_eq!(annotation.comparison_str(&interner), Some(">10"));
+    }
+
+    #[test]
+    fn unresolvable_cv_prefix_has_no_curie_but_keeps_the_raw_accession() {
+        let mut interner = Interner::new();
+        let annotation = SDRFAnnotation::parse("NT=something;AC=NOTACV:123", &mut interner).unwrap();
+        assert!(annotation.curie(&interner).is_none());
+        assert_eq!(annotation.accession_str(&interner), Some("NOTACV:123"));
+    }
+
+    #[test]
+    fn plain_values_are_not_annotations() {
+        let mut interner = Interner::new();
+        assert!(SDRFAnnotation::parse("not applicable", &mut interner).is_none());
+        assert!(SDRFAnnotation::parse(
+            "http://purl.obolibrary.org/obo/UBERON_0002037",
+            &mut interner
+        )
+        .is_none());
+    }
+}